@@ -0,0 +1,132 @@
+use std::time::Instant;
+
+#[cfg(all(unix, feature = "cpu-time"))]
+mod cpu_time {
+    use libc::funcs::posix01::resource::getrusage;
+    use libc::types::os::common::bsd43::rusage;
+
+    pub fn process_time_us() -> u64 {
+        unsafe {
+            let mut u: rusage = std::mem::zeroed();
+            getrusage(0, &mut u);
+            (u.ru_utime.tv_sec as u64) * 1_000_000 + (u.ru_utime.tv_usec as u64)
+        }
+    }
+}
+
+#[cfg(all(unix, feature = "cpu-time"))]
+fn elapsed_us<F: FnMut()>(func: &mut F) -> u64 {
+    let before = cpu_time::process_time_us();
+    func();
+    cpu_time::process_time_us() - before
+}
+
+#[cfg(not(all(unix, feature = "cpu-time")))]
+fn elapsed_us<F: FnMut()>(func: &mut F) -> u64 {
+    let before = Instant::now();
+    func();
+    let elapsed = before.elapsed();
+    elapsed.as_secs() * 1_000_000 + (elapsed.subsec_nanos() as u64) / 1_000
+}
+
+/// The individual timing samples collected by a `Harness` run, along with
+/// the descriptive statistics the benchmark comparison cares about.
+pub struct HarnessResult {
+    pub samples: Vec<u64>,
+}
+
+impl HarnessResult {
+    /// `None` if `samples` is empty (a zero-sample `Harness::run`).
+    pub fn min(&self) -> Option<u64> {
+        self.samples.iter().cloned().min()
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        if self.samples.is_empty() {
+            None
+        } else {
+            Some(self.samples.iter().sum::<u64>() as f64 / self.samples.len() as f64)
+        }
+    }
+
+    pub fn median(&self) -> Option<u64> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted = self.samples.clone();
+        sorted.sort();
+        Some(sorted[sorted.len() / 2])
+    }
+
+    pub fn stddev(&self) -> Option<f64> {
+        let mean = match self.mean() {
+            Some(mean) => mean,
+            None => return None
+        };
+        let variance = self.samples.iter()
+            .map(|&x| { let d = x as f64 - mean; d * d })
+            .sum::<f64>() / self.samples.len() as f64;
+        Some(variance.sqrt())
+    }
+}
+
+/// Runs a closure through a warm-up phase followed by `sample_count`
+/// independently timed samples, mirroring the sampling approach of
+/// `#[bench]`: a single aggregate measurement hides how noisy the
+/// individual runs are, so we keep every sample instead of just the total.
+pub struct Harness {
+    pub sample_count: u64,
+    pub warmup_samples: u64,
+}
+
+impl Harness {
+    pub fn new(sample_count: u64) -> Harness {
+        Harness { sample_count: sample_count, warmup_samples: 3 }
+    }
+
+    pub fn run<F: FnMut()>(&self, mut func: F) -> HarnessResult {
+        for _ in 0..self.warmup_samples {
+            func();
+        }
+
+        let mut samples = Vec::with_capacity(self.sample_count as usize);
+        for _ in 0..self.sample_count {
+            samples.push(elapsed_us(&mut func));
+        }
+
+        HarnessResult { samples: samples }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    #[test]
+    fn collects_one_sample_per_run_after_warming_up() {
+        let calls = Cell::new(0u64);
+        let harness = Harness { sample_count: 5, warmup_samples: 3 };
+        let result = harness.run(|| { calls.set(calls.get() + 1); });
+
+        assert_eq!(result.samples.len(), 5);
+        assert_eq!(calls.get(), 8); // warmup_samples + sample_count
+
+        assert!(result.min().is_some());
+        assert!(result.mean().is_some());
+        assert!(result.median().is_some());
+        assert!(result.stddev().is_some());
+    }
+
+    #[test]
+    fn a_zero_sample_run_reports_no_statistics_instead_of_panicking() {
+        let harness = Harness { sample_count: 0, warmup_samples: 1 };
+        let result = harness.run(|| {});
+
+        assert_eq!(result.samples.len(), 0);
+        assert_eq!(result.min(), None);
+        assert_eq!(result.mean(), None);
+        assert_eq!(result.median(), None);
+        assert_eq!(result.stddev(), None);
+    }
+}
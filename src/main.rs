@@ -1,50 +1,36 @@
 extern crate libc;
 
 mod benchmark;
+mod harness;
 mod parser;
 use benchmark::Benchmark;
-use parser::Parser;
+use harness::Harness;
+use parser::{IoSource, Parser};
 
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use libc::funcs::posix01::resource::getrusage;
-use libc::types::os::common::bsd43::{rusage};
-
-fn get_process_time_us() -> u64 {
-    unsafe {
-        let mut u: rusage = std::mem::uninitialized();
-        getrusage(0, &mut u);
-        (u.ru_utime.tv_sec as u64) * 1000000 + (u.ru_utime.tv_usec as u64)
-    }
-}
-
-fn time_lambda<F: FnOnce()>(func: F) -> u64 {
-    let before = get_process_time_us();
-    func();
-    let after = get_process_time_us();
-    after - before
-}
-
-fn run_benchmark<B: Benchmark>(benchmark: B, description: &'static str, iterations: u64) -> u64 {
+fn run_benchmark<B: Benchmark>(benchmark: B, description: &'static str, sample_count: u64) {
     let mut csv: File = OpenOptions::new().append(true).write(true).open("results.csv").unwrap();
-    print!("{0: >#20}  {1: <#50}  ", "rustc", description);
+    let result = Harness::new(sample_count).run(|| { benchmark.run(); });
 
-    let us = time_lambda(move || {
-        for _ in (0..iterations) {
-            benchmark.run();
-        }
-    });
+    match (result.min(), result.mean(), result.median(), result.stddev()) {
+        (Some(min), Some(mean), Some(median), Some(stddev)) => {
+            println!("{0: >#20}  {1: <#50}  min={2: >#7}µs  mean={3: >#9.1}µs  median={4: >#7}µs  stddev={5: >#7.1}µs",
+                      "rustc", description, min, mean, median, stddev);
+        },
+        _ => println!("{0: >#20}  {1: <#50}  (no samples)", "rustc", description)
+    }
 
-    println!("{0: >#10}µs", us);
-    writeln!(csv, "{0};{1};{2}", "rustc", description, us).unwrap();
-    
-    us
+    for sample in &result.samples {
+        writeln!(csv, "{0};{1};{2}", "rustc", description, sample).unwrap();
+    }
 }
 
 struct BenchmarkParser {
-    program: String
+    program: String,
+    parser: Parser,
 }
 
 impl BenchmarkParser {
@@ -52,13 +38,35 @@ impl BenchmarkParser {
         let mut file = OpenOptions::new().read(true).open(path).unwrap();
         let mut program: String = Default::default();
         file.read_to_string(&mut program).unwrap();
-        BenchmarkParser { program: program }
+        BenchmarkParser { program: program, parser: Parser::new() }
     }
 }
 
 impl Benchmark for BenchmarkParser {
     fn run(&self) -> i64 {
-        let result = Parser.execute(&self.program);
+        let result = self.parser.execute(&self.program);
+        result as i64
+    }
+}
+
+// Streams the program from disk instead of reading it into a `String`
+// up front, so the benchmark can show the cost (or saving) of avoiding
+// that allocation.
+struct StreamingBenchmarkParser {
+    path: PathBuf,
+    parser: Parser,
+}
+
+impl StreamingBenchmarkParser {
+    fn new<P: AsRef<Path>>(path: P) -> StreamingBenchmarkParser {
+        StreamingBenchmarkParser { path: path.as_ref().to_path_buf(), parser: Parser::new() }
+    }
+}
+
+impl Benchmark for StreamingBenchmarkParser {
+    fn run(&self) -> i64 {
+        let file = OpenOptions::new().read(true).open(&self.path).unwrap();
+        let result = self.parser.try_execute_from(IoSource::new(file)).unwrap_or(0);
         result as i64
     }
 }
@@ -66,17 +74,22 @@ impl Benchmark for BenchmarkParser {
 fn main() {
     let args: Vec<_> = std::env::args().collect();
     if args.len() != 2 {
-        println!("Please provide number of iterations as first argument.");
+        println!("Please provide number of samples as first argument.");
         std::process::exit(1);
     }
-    let iterations = match args[1].parse::<u64>() {
+    let sample_count = match args[1].parse::<u64>() {
         Ok(x) => x,
         Err(err) => {
-            println!("Please provice number of iterations as first argument (error: {0})", err);
+            println!("Please provice number of samples as first argument (error: {0})", err);
             std::process::exit(1)
         }
     };
+    if sample_count < 1 {
+        println!("Number of samples must be at least 1.");
+        std::process::exit(1);
+    }
 
-    run_benchmark(BenchmarkParser::new("input.ok"), "parser-results-no-errors", iterations);
-    run_benchmark(BenchmarkParser::new("input.err"), "parser-results-with-errors", iterations);
+    run_benchmark(BenchmarkParser::new("input.ok"), "parser-results-no-errors", sample_count);
+    run_benchmark(BenchmarkParser::new("input.err"), "parser-results-with-errors", sample_count);
+    run_benchmark(StreamingBenchmarkParser::new("input.ok"), "parser-results-no-errors-streaming", sample_count);
 }
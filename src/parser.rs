@@ -1,113 +1,812 @@
-use std::str::Chars;
+use std::collections::HashMap;
+use std::io::Read;
+use std::rc::Rc;
+use std::str;
 
-#[derive(Copy,Clone)]
-pub struct Parser;
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub line: u32,
+    pub column: u32,
+    pub byte_offset: usize,
+}
+
+impl Position {
+    fn start() -> Position {
+        Position { line: 1, column: 1, byte_offset: 0 }
+    }
+
+    fn advance(&mut self, c: char) {
+        self.byte_offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+    }
+}
+
+/// The grammar a `Parser` accepts: operators either come before their
+/// operands (`+ 1 2`) or between them with the usual precedence and
+/// left-associativity (`1 + 2`).
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Notation {
+    Prefix,
+    Infix,
+}
+
+/// A kind of grouping bracket a `Parser` will accept around a
+/// sub-expression.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Bracket {
+    Paren,
+    Square,
+    Curly,
+}
+
+impl Bracket {
+    fn open(&self) -> char {
+        match *self {
+            Bracket::Paren => '(',
+            Bracket::Square => '[',
+            Bracket::Curly => '{',
+        }
+    }
+
+    fn close(&self) -> char {
+        match *self {
+            Bracket::Paren => ')',
+            Bracket::Square => ']',
+            Bracket::Curly => '}',
+        }
+    }
+}
+
+/// Configuration for a `Parser`: which notation to read, which brackets
+/// are allowed around a sub-expression, and any extra binary operators
+/// beyond the built-in `+ - * /`.
+pub struct ParserOptions {
+    notation: Notation,
+    brackets: Vec<Bracket>,
+    operators: HashMap<char, Box<Fn(i64, i64) -> i64>>,
+}
+
+impl ParserOptions {
+    pub fn new(notation: Notation) -> ParserOptions {
+        ParserOptions {
+            notation: notation,
+            brackets: vec![Bracket::Paren],
+            operators: HashMap::new(),
+        }
+    }
+
+    pub fn with_brackets(mut self, brackets: Vec<Bracket>) -> ParserOptions {
+        self.brackets = brackets;
+        self
+    }
+
+    /// Register an extra binary operator under `symbol`, taking priority
+    /// over any of the built-in `+ - * /` registered under the same
+    /// symbol. In infix notation, a non-built-in symbol binds at the same
+    /// precedence as `+`/`-`.
+    pub fn with_operator<F>(mut self, symbol: char, f: F) -> ParserOptions
+        where F: Fn(i64, i64) -> i64 + 'static
+    {
+        self.operators.insert(symbol, Box::new(f));
+        self
+    }
+}
+
+impl Default for ParserOptions {
+    fn default() -> ParserOptions {
+        ParserOptions::new(Notation::Prefix)
+    }
+}
+
+/// The longest a UTF-8 encoding of a single `char` can be.
+const MAX_UTF8_BYTES: usize = 4;
+
+/// Signals that the next character could not be decoded at all (as opposed
+/// to simply running out of input), either because the bytes read are not
+/// valid UTF-8 or because the underlying `Read` returned an error.
+#[derive(Copy, Clone, Debug)]
+pub struct DecodeError;
+
+/// A stream of characters a `Parser` can consume. Implemented for `&str`
+/// (borrowing the whole input up front) and for any `std::io::Read`
+/// (decoding UTF-8 lazily, one character at a time), so the parser is not
+/// tied to having the entire program materialized in memory.
+pub trait Source {
+    fn next_char(&mut self) -> Result<Option<char>, DecodeError>;
+    fn peek_char(&mut self) -> Result<Option<char>, DecodeError>;
+}
+
+impl<'a> Source for &'a str {
+    fn next_char(&mut self) -> Result<Option<char>, DecodeError> {
+        let mut chars = self.chars();
+        let c = chars.next();
+        *self = chars.as_str();
+        Ok(c)
+    }
+
+    fn peek_char(&mut self) -> Result<Option<char>, DecodeError> {
+        Ok(self.chars().next())
+    }
+}
+
+/// A `Source` that decodes UTF-8 lazily from any `std::io::Read`,
+/// buffering only the bytes of the character currently being decoded.
+pub struct IoSource<R> {
+    reader: R,
+    buf: Vec<u8>,
+    peeked: Option<Result<Option<char>, DecodeError>>,
+}
+
+impl<R: Read> IoSource<R> {
+    pub fn new(reader: R) -> IoSource<R> {
+        IoSource { reader: reader, buf: Vec::new(), peeked: None }
+    }
+
+    // An invalid leading byte can never become valid by reading more bytes
+    // after it, so we bail out on the first decode error instead of looping
+    // until EOF and silently discarding everything after the bad byte.
+    fn read_char(&mut self) -> Result<Option<char>, DecodeError> {
+        self.buf.clear();
+        let mut byte = [0u8; 1];
+        loop {
+            match self.reader.read(&mut byte) {
+                Ok(0) => return if self.buf.is_empty() { Ok(None) } else { Err(DecodeError) },
+                Ok(_) => {
+                    self.buf.push(byte[0]);
+                    match str::from_utf8(&self.buf) {
+                        Ok(s) => return Ok(s.chars().next()),
+                        Err(ref e) if e.error_len().is_some() => return Err(DecodeError),
+                        Err(_) if self.buf.len() >= MAX_UTF8_BYTES => return Err(DecodeError),
+                        Err(_) => continue
+                    }
+                },
+                Err(_) => return Err(DecodeError)
+            }
+        }
+    }
+}
+
+impl<R: Read> Source for IoSource<R> {
+    fn next_char(&mut self) -> Result<Option<char>, DecodeError> {
+        match self.peeked.take() {
+            Some(peeked) => peeked,
+            None => self.read_char()
+        }
+    }
 
-struct ParserImpl<'a> {
-    iter: Chars<'a>
+    // A decode error is kept as the peeked result, so the next `next_char`
+    // reports it instead of reading on past the bad bytes.
+    fn peek_char(&mut self) -> Result<Option<char>, DecodeError> {
+        if self.peeked.is_none() {
+            self.peeked = Some(self.read_char());
+        }
+        self.peeked.unwrap()
+    }
 }
 
-enum Error {
-    InvalidOperator,
-    InvalidCharacter,
-    UnexpectedEOF,
+#[derive(Clone)]
+pub struct Parser {
+    options: Rc<ParserOptions>,
 }
 
-enum Op {
-    Add,
-    Sub,
-    Mul,
-    Div
+struct ParserImpl<'o, S> {
+    source: S,
+    pos: Position,
+    options: &'o ParserOptions,
+    env: HashMap<String, i64>,
+    functions: HashMap<String, (Vec<String>, Expr)>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    InvalidOperator(Position),
+    InvalidCharacter(Position, char),
+    UnexpectedEOF(Position),
+    DivisionByZero(Position),
+    Overflow(Position),
+    UnknownName(Position, String),
+    ArityMismatch(Position, String, usize, usize),
+    /// The source produced bytes that could not be decoded as UTF-8 (or the
+    /// underlying `Read` failed outright), as distinct from `UnexpectedEOF`.
+    InvalidEncoding(Position),
+}
+
+impl Error {
+    /// The position in the source at which this error occurred.
+    pub fn position(&self) -> Position {
+        match *self {
+            Error::InvalidOperator(pos) => pos,
+            Error::InvalidCharacter(pos, _) => pos,
+            Error::UnexpectedEOF(pos) => pos,
+            Error::DivisionByZero(pos) => pos,
+            Error::Overflow(pos) => pos,
+            Error::UnknownName(pos, _) => pos,
+            Error::ArityMismatch(pos, _, _, _) => pos,
+            Error::InvalidEncoding(pos) => pos,
+        }
+    }
 }
 
 pub type ParserResult<T> = Result<T, Error>;
 
+/// A parsed (but not yet evaluated) expression. Kept around as a tree
+/// rather than folded into an `i64` immediately, because a `fn` body may
+/// be evaluated more than once (once per call, with different argument
+/// bindings).
+#[derive(Clone)]
+enum Expr {
+    Number(i64),
+    Name(Position, String),
+    BinOp(Position, char, Box<Expr>, Box<Expr>),
+    Call(Position, String, Vec<Expr>),
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_identifier_continue(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
 impl Parser {
+    pub fn new() -> Parser {
+        Parser { options: Rc::new(ParserOptions::default()) }
+    }
+
+    pub fn with_options(options: ParserOptions) -> Parser {
+        Parser { options: Rc::new(options) }
+    }
+
     pub fn execute<'a>(&self, program: &'a String) -> i64 {
-        let mut p: ParserImpl = ParserImpl{iter: program.chars()};
-        match p.expression() {
+        match self.try_execute(program) {
             Ok(x) => x,
             Err(_) => 0
         }
     }
+
+    pub fn try_execute<'a>(&self, program: &'a str) -> ParserResult<i64> {
+        self.try_execute_from(program)
+    }
+
+    pub fn try_execute_from<S: Source>(&self, source: S) -> ParserResult<i64> {
+        let mut p = ParserImpl {
+            source: source,
+            pos: Position::start(),
+            options: &self.options,
+            env: HashMap::new(),
+            functions: HashMap::new(),
+        };
+        p.program()
+    }
+}
+
+impl Default for Parser {
+    fn default() -> Parser {
+        Parser::new()
+    }
 }
 
-impl<'a> ParserImpl<'a> {
-    fn inner_expression<'b>(&'b mut self) -> ParserResult<i64> {
-        self.operation().and_then(|op| {
+impl<'o, S: Source> ParserImpl<'o, S> {
+    // Parses `((let NAME = EXPR | fn NAME(args) = EXPR) ;)* EXPR`, i.e. a
+    // run of variable/function definitions in any order followed by one trailing
+    // expression whose value is the result of the program.
+    fn program<'b>(&'b mut self) -> ParserResult<i64> {
+        loop {
+            self.skip_whitespace();
+            let pos = self.pos;
+            if !is_identifier_start(self.peek()) {
+                let expr = try!(self.expression());
+                return self.eval(&expr);
+            }
+
+            let name = try!(self.identifier());
+            match name.as_ref() {
+                "let" => try!(self.let_statement()),
+                "fn" => try!(self.fn_statement()),
+                _ => {
+                    let expr = try!(self.expression_from_name(pos, name));
+                    return self.eval(&expr);
+                }
+            }
+        }
+    }
+
+    // `let NAME = EXPR ;`, with the keyword already consumed.
+    fn let_statement<'b>(&'b mut self) -> ParserResult<()> {
+        self.skip_whitespace();
+        let name = try!(self.identifier());
+        self.skip_whitespace();
+        try!(self.expect_char('='));
+        self.skip_whitespace();
+        let expr = try!(self.expression());
+        self.skip_whitespace();
+        try!(self.expect_char(';'));
+        let value = try!(self.eval(&expr));
+        self.env.insert(name, value);
+        Ok(())
+    }
+
+    // `fn NAME(params) = EXPR ;`, with the keyword already consumed.
+    fn fn_statement<'b>(&'b mut self) -> ParserResult<()> {
+        self.skip_whitespace();
+        let name = try!(self.identifier());
+        self.skip_whitespace();
+        try!(self.expect_char('('));
+        let params = try!(self.parameter_list());
+        self.skip_whitespace();
+        try!(self.expect_char('='));
+        self.skip_whitespace();
+        let body = try!(self.expression());
+        self.skip_whitespace();
+        try!(self.expect_char(';'));
+        self.functions.insert(name, (params, body));
+        Ok(())
+    }
+
+    fn parameter_list<'b>(&'b mut self) -> ParserResult<Vec<String>> {
+        let mut params = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == ')' {
+            try!(self.get_char());
+            return Ok(params);
+        }
+        loop {
+            self.skip_whitespace();
+            // A trailing comma (`fn add(a,)`) would otherwise make
+            // `identifier()` read an empty name here instead of erroring.
+            if self.peek() == ')' {
+                return Err(Error::InvalidCharacter(self.pos, ')'));
+            }
+            params.push(try!(self.identifier()));
+            self.skip_whitespace();
+            match try!(self.get_char()) {
+                ',' => continue,
+                ')' => break,
+                x => return Err(Error::InvalidCharacter(self.pos, x))
+            }
+        }
+        Ok(params)
+    }
+
+    fn argument_list<'b>(&'b mut self) -> ParserResult<Vec<Expr>> {
+        let mut args = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == ')' {
+            try!(self.get_char());
+            return Ok(args);
+        }
+        loop {
+            self.skip_whitespace();
+            // A trailing comma (`add(1,)`) would otherwise make
+            // `expression()` read an implicit `0` here instead of erroring.
+            if self.peek() == ')' {
+                return Err(Error::InvalidCharacter(self.pos, ')'));
+            }
+            args.push(try!(self.expression()));
+            self.skip_whitespace();
+            match try!(self.get_char()) {
+                ',' => continue,
+                ')' => break,
+                x => return Err(Error::InvalidCharacter(self.pos, x))
+            }
+        }
+        Ok(args)
+    }
+
+    // Evaluates an already-parsed expression against the current
+    // variable/function environment.
+    fn eval<'b>(&'b mut self, expr: &Expr) -> ParserResult<i64> {
+        match *expr {
+            Expr::Number(n) => Ok(n),
+            Expr::Name(pos, ref name) => {
+                match self.env.get(name) {
+                    Some(&value) => Ok(value),
+                    None => Err(Error::UnknownName(pos, name.clone()))
+                }
+            },
+            Expr::BinOp(pos, op, ref left, ref right) => {
+                let left = try!(self.eval(left));
+                let right = try!(self.eval(right));
+                self.eval_operator(op, pos, left, right)
+            },
+            Expr::Call(pos, ref name, ref args) => {
+                let (params, body) = match self.functions.get(name) {
+                    Some(f) => f.clone(),
+                    None => return Err(Error::UnknownName(pos, name.clone()))
+                };
+
+                if params.len() != args.len() {
+                    return Err(Error::ArityMismatch(pos, name.clone(), params.len(), args.len()));
+                }
+
+                let mut values = Vec::with_capacity(args.len());
+                for arg in args.iter() {
+                    values.push(try!(self.eval(arg)));
+                }
+
+                // Dynamically scoped: callee sees its own bindings layered
+                // on top of the caller's, then the caller's are restored.
+                let saved_env = self.env.clone();
+                for (param, value) in params.iter().zip(values.into_iter()) {
+                    self.env.insert(param.clone(), value);
+                }
+                let result = self.eval(&body);
+                self.env = saved_env;
+                result
+            }
+        }
+    }
+
+    fn inner_expression<'b>(&'b mut self) -> ParserResult<Expr> {
+        self.operation().and_then(|(op, pos)| {
             self.expression().and_then(|left| {
                 self.expression().and_then(|right| {
-                    match op {
-                        Op::Add => Ok(left + right),
-                        Op::Sub => Ok(left - right),
-                        Op::Mul => Ok(left * right),
-                        Op::Div => Ok(left / right)
-                    }
+                    Ok(Expr::BinOp(pos, op, Box::new(left), Box::new(right)))
                 })
             })
         })
     }
 
-    fn expression<'b>(&'b mut self) -> ParserResult<i64> {
+    fn expression<'b>(&'b mut self) -> ParserResult<Expr> {
+        match self.options.notation {
+            Notation::Prefix => self.prefix_expression(),
+            Notation::Infix => self.infix_sum(),
+        }
+    }
+
+    // An identifier has already been consumed as the leading token of an
+    // expression (used by `program` to disambiguate `let`/`fn` from a
+    // variable reference or call starting the trailing expression).
+    fn expression_from_name<'b>(&'b mut self, pos: Position, name: String) -> ParserResult<Expr> {
+        let atom = try!(self.name_or_call(pos, name));
+        match self.options.notation {
+            Notation::Prefix => Ok(atom),
+            Notation::Infix => {
+                let product = try!(self.infix_product_continue(atom));
+                self.infix_sum_continue(product)
+            }
+        }
+    }
+
+    fn name_or_call<'b>(&'b mut self, pos: Position, name: String) -> ParserResult<Expr> {
         self.skip_whitespace();
+        if self.peek() == '(' {
+            try!(self.get_char());
+            let args = try!(self.argument_list());
+            Ok(Expr::Call(pos, name, args))
+        } else {
+            Ok(Expr::Name(pos, name))
+        }
+    }
+
+    fn prefix_expression<'b>(&'b mut self) -> ParserResult<Expr> {
+        self.skip_whitespace();
+        let pos = self.pos;
         match self.peek() {
-            '(' => {
-                self.iter.next();
+            c if self.bracket_for_open(c).is_some() => {
+                let bracket = self.bracket_for_open(c).unwrap();
+                try!(self.get_char());
                 self.skip_whitespace();
-                let val = try!(self.expression());
+                let val = try!(self.prefix_expression());
                 self.skip_whitespace();
-                try!(self.expect_char(')'));
+                try!(self.expect_char(bracket.close()));
                 Ok(val)
             },
             '0'...'9' => self.number(),
+            c if is_identifier_start(c) => {
+                let name = try!(self.identifier());
+                self.name_or_call(pos, name)
+            },
             _ => self.inner_expression()
         }
     }
 
-    fn operation<'b>(&'b mut self) -> ParserResult<Op> {
-        Ok(match try!(self.get_char()) {
-            '+' => Op::Add,
-            '-' => Op::Sub,
-            '*' => Op::Mul,
-            '/' => Op::Div,
-            _ => return Err(Error::InvalidOperator)
-        })
+    // Standard `sum := product (('+' | '-' | custom) product)*` precedence
+    // climbing for infix notation; `*`/`/` bind tighter than `+`/`-`.
+    fn infix_sum<'b>(&'b mut self) -> ParserResult<Expr> {
+        let left = try!(self.infix_product());
+        self.infix_sum_continue(left)
+    }
+
+    fn infix_sum_continue<'b>(&'b mut self, mut left: Expr) -> ParserResult<Expr> {
+        loop {
+            self.skip_whitespace();
+            let pos = self.pos;
+            let c = self.peek();
+            if c == '+' || c == '-' || self.is_custom_operator(c) {
+                try!(self.get_char());
+                let right = try!(self.infix_product());
+                left = Expr::BinOp(pos, c, Box::new(left), Box::new(right));
+            } else {
+                break;
+            }
+        }
+        Ok(left)
+    }
+
+    fn infix_product<'b>(&'b mut self) -> ParserResult<Expr> {
+        let left = try!(self.infix_primary());
+        self.infix_product_continue(left)
+    }
+
+    fn infix_product_continue<'b>(&'b mut self, mut left: Expr) -> ParserResult<Expr> {
+        loop {
+            self.skip_whitespace();
+            let pos = self.pos;
+            let c = self.peek();
+            if c == '*' || c == '/' {
+                try!(self.get_char());
+                let right = try!(self.infix_primary());
+                left = Expr::BinOp(pos, c, Box::new(left), Box::new(right));
+            } else {
+                break;
+            }
+        }
+        Ok(left)
+    }
+
+    fn infix_primary<'b>(&'b mut self) -> ParserResult<Expr> {
+        self.skip_whitespace();
+        let pos = self.pos;
+        let c = self.peek();
+        match self.bracket_for_open(c) {
+            Some(bracket) => {
+                try!(self.get_char());
+                self.skip_whitespace();
+                let val = try!(self.infix_sum());
+                self.skip_whitespace();
+                try!(self.expect_char(bracket.close()));
+                Ok(val)
+            },
+            None if is_identifier_start(c) => {
+                let name = try!(self.identifier());
+                self.name_or_call(pos, name)
+            },
+            None => self.number()
+        }
+    }
+
+    fn bracket_for_open(&self, c: char) -> Option<Bracket> {
+        self.options.brackets.iter().cloned().find(|b| b.open() == c)
+    }
+
+    fn is_custom_operator(&self, c: char) -> bool {
+        self.options.operators.contains_key(&c)
+    }
+
+    fn eval_operator(&self, op: char, pos: Position, left: i64, right: i64) -> ParserResult<i64> {
+        if let Some(f) = self.options.operators.get(&op) {
+            return Ok(f(left, right));
+        }
+        match op {
+            '+' => left.checked_add(right).ok_or(Error::Overflow(pos)),
+            '-' => left.checked_sub(right).ok_or(Error::Overflow(pos)),
+            '*' => left.checked_mul(right).ok_or(Error::Overflow(pos)),
+            '/' if right == 0 => Err(Error::DivisionByZero(pos)),
+            '/' => left.checked_div(right).ok_or(Error::Overflow(pos)),
+            _ => unreachable!("operation() only accepts built-in or registered operators")
+        }
+    }
+
+    fn operation<'b>(&'b mut self) -> ParserResult<(char, Position)> {
+        let pos = self.pos;
+        let c = try!(self.get_char());
+        match c {
+            '+' | '-' | '*' | '/' => Ok((c, pos)),
+            c if self.is_custom_operator(c) => Ok((c, pos)),
+            _ => Err(Error::InvalidOperator(pos))
+        }
     }
 
-    fn number<'b>(&'b mut self) -> ParserResult<i64> {
+    fn identifier<'b>(&'b mut self) -> ParserResult<String> {
+        let mut name = String::new();
+        while is_identifier_continue(self.peek()) {
+            name.push(try!(self.get_char()));
+        }
+        Ok(name)
+    }
+
+    fn number<'b>(&'b mut self) -> ParserResult<Expr> {
+        let pos = self.pos;
         let mut result: i64 = 0;
         while char::is_digit(self.peek(), 10) {
             let c = try!(self.get_char());
-            result *= 10;
-            result += c.to_digit(10).unwrap() as i64
+            let digit = c.to_digit(10).unwrap() as i64;
+            result = try!(result.checked_mul(10).and_then(|r| r.checked_add(digit))
+                .ok_or(Error::Overflow(pos)));
         }
-        Ok(result)
+        Ok(Expr::Number(result))
     }
 
     fn expect_char<'b>(&'b mut self, c: char) -> ParserResult<char> {
+        let pos = self.pos;
         match try!(self.get_char()) {
             x if x == c => Ok(c),
-            _ => Err(Error::InvalidCharacter)
+            x => Err(Error::InvalidCharacter(pos, x))
         }
     }
 
     fn get_char<'b>(&'b mut self) -> ParserResult<char> {
-        match self.iter.next() {
-            Some(x) => Ok(x),
-            None => Err(Error::UnexpectedEOF)
+        match self.source.next_char() {
+            Ok(Some(x)) => {
+                self.pos.advance(x);
+                Ok(x)
+            },
+            Ok(None) => Err(Error::UnexpectedEOF(self.pos)),
+            Err(_) => Err(Error::InvalidEncoding(self.pos))
         }
     }
 
+    // Best-effort look-ahead: both EOF and a decode error are reported as
+    // `Default::default()` here, since the real error is only raised once
+    // `get_char` actually tries to consume the offending character.
     fn peek<'b>(&'b mut self) -> char {
-        match self.iter.clone().peekable().peek() {
-            Some(x) => *x,
-            None => Default::default()
+        match self.source.peek_char() {
+            Ok(Some(x)) => x,
+            Ok(None) | Err(_) => Default::default()
         }
     }
 
     fn skip_whitespace<'b>(&'b mut self) {
         while char::is_whitespace(self.peek()) {
-            self.iter.next();
+            if let Ok(Some(c)) = self.source.next_char() {
+                self.pos.advance(c);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn reports_the_position_of_an_invalid_operator() {
+        let err = Parser::new().try_execute("? 1 2").unwrap_err();
+        match err {
+            Error::InvalidOperator(pos) => {
+                assert_eq!(pos.line, 1);
+                assert_eq!(pos.column, 1);
+            },
+            other => panic!("expected InvalidOperator, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn reports_the_line_and_column_of_an_error_after_a_newline() {
+        let err = Parser::new().try_execute("+ 1\n ~ 2").unwrap_err();
+        assert_eq!(err.position().line, 2);
+        assert_eq!(err.position().column, 2);
+    }
+
+    #[test]
+    fn infix_notation_respects_operator_precedence_and_parens() {
+        let parser = Parser::with_options(ParserOptions::new(Notation::Infix));
+        assert_eq!(parser.try_execute("1 + 2 * 3").unwrap(), 7);
+        assert_eq!(parser.try_execute("(1 + 2) * 3").unwrap(), 9);
+    }
+
+    #[test]
+    fn infix_notation_accepts_square_and_curly_brackets() {
+        let parser = Parser::with_options(
+            ParserOptions::new(Notation::Infix)
+                .with_brackets(vec![Bracket::Square, Bracket::Curly]));
+        assert_eq!(parser.try_execute("[1 + 2] * {3}").unwrap(), 9);
+    }
+
+    #[test]
+    fn a_registered_operator_overrides_a_built_in_symbol() {
+        let parser = Parser::with_options(
+            ParserOptions::new(Notation::Infix)
+                .with_operator('+', |a, b| a * b));
+        assert_eq!(parser.try_execute("2 + 3").unwrap(), 6);
+    }
+
+    #[test]
+    fn calling_a_function_with_too_few_arguments_is_an_error() {
+        let err = Parser::new().try_execute("let b = 999; fn add(a, b) = + a b; add(1)").unwrap_err();
+        match err {
+            Error::ArityMismatch(_, ref name, 2, 1) => assert_eq!(name, "add"),
+            other => panic!("expected ArityMismatch(.., \"add\", 2, 1), got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn calling_a_function_with_too_many_arguments_is_an_error() {
+        let err = Parser::new().try_execute("fn add(a) = + a 1; add(1, 2)").unwrap_err();
+        match err {
+            Error::ArityMismatch(_, ref name, 1, 2) => assert_eq!(name, "add"),
+            other => panic!("expected ArityMismatch(.., \"add\", 1, 2), got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn a_correctly_called_function_does_not_see_the_caller_s_stale_bindings() {
+        let result = Parser::new().try_execute("let b = 999; fn add(a, b) = + a b; add(1, 2)").unwrap();
+        assert_eq!(result, 3);
+    }
+
+    #[test]
+    fn a_literal_too_large_for_i64_reports_overflow_instead_of_panicking() {
+        let err = Parser::new().try_execute("99999999999999999999").unwrap_err();
+        match err {
+            Error::Overflow(_) => (),
+            other => panic!("expected Overflow, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn division_by_zero_is_a_positioned_error_not_a_panic() {
+        let err = Parser::new().try_execute("/ 1 0").unwrap_err();
+        match err {
+            Error::DivisionByZero(_) => (),
+            other => panic!("expected DivisionByZero, got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn a_registered_operator_is_usable_under_a_new_symbol() {
+        let parser = Parser::with_options(
+            ParserOptions::new(Notation::Prefix)
+                .with_operator('^', |a, b| a * b));
+        assert_eq!(parser.try_execute("^ 2 3").unwrap(), 6);
+    }
+
+    #[test]
+    fn reads_a_program_from_any_std_io_read_via_io_source() {
+        let source = IoSource::new(Cursor::new(b"+ 1 2".to_vec()));
+        let result = Parser::new().try_execute_from(source).unwrap();
+        assert_eq!(result, 3);
+    }
+
+    #[test]
+    fn a_let_bound_variable_is_readable_by_name() {
+        let result = Parser::new().try_execute("let x = 5; x").unwrap();
+        assert_eq!(result, 5);
+    }
+
+    #[test]
+    fn referencing_an_undefined_name_is_an_unknown_name_error() {
+        let err = Parser::new().try_execute("x").unwrap_err();
+        match err {
+            Error::UnknownName(_, ref name) => assert_eq!(name, "x"),
+            other => panic!("expected UnknownName(.., \"x\"), got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn a_trailing_comma_in_a_parameter_list_is_a_parse_error_not_an_empty_name() {
+        let err = Parser::new().try_execute("fn add(a,) = a; add(1)").unwrap_err();
+        match err {
+            Error::InvalidCharacter(_, ')') => (),
+            other => panic!("expected InvalidCharacter(.., ')'), got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn a_trailing_comma_in_an_argument_list_is_a_parse_error_not_an_implicit_zero() {
+        let err = Parser::new().try_execute("fn add(a) = a; add(1,)").unwrap_err();
+        match err {
+            Error::InvalidCharacter(_, ')') => (),
+            other => panic!("expected InvalidCharacter(.., ')'), got {:?}", other)
+        }
+    }
+
+    #[test]
+    fn invalid_utf8_from_an_io_source_is_reported_as_invalid_encoding_not_eof() {
+        // 0xff is not a valid UTF-8 leading byte under any continuation.
+        let source = IoSource::new(Cursor::new(vec![b'+', b' ', b'1', b' ', 0xff]));
+        let err = Parser::new().try_execute_from(source).unwrap_err();
+        match err {
+            Error::InvalidEncoding(_) => (),
+            other => panic!("expected InvalidEncoding, got {:?}", other)
         }
     }
 }